@@ -0,0 +1,315 @@
+//! Parallel deletion engine.
+//!
+//! The scanner collects every matching directory, then this module sizes and
+//! removes them across a bounded worker pool, aggregating per-directory
+//! results so a single failure doesn't abort the whole run.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use walkdir::WalkDir;
+
+const GIB: f64 = (1024u64 * 1024 * 1024) as f64;
+
+/// What the engine does with each matched directory.
+pub enum Mode {
+    /// Report only; nothing is touched.
+    DryRun,
+    /// Permanently remove the directory.
+    Delete,
+    /// Move the directory into the given backup root instead of deleting it.
+    Trash(PathBuf),
+}
+
+impl Mode {
+    /// Whether this mode actually mutates the filesystem.
+    fn commits(&self) -> bool {
+        !matches!(self, Mode::DryRun)
+    }
+}
+
+/// Outcome of processing a single matched directory.
+pub struct DirOutcome {
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub result: io::Result<()>,
+    /// Where the directory was moved in `--trash` mode, if anywhere.
+    pub moved_to: Option<PathBuf>,
+}
+
+/// Aggregate result of a deletion run.
+pub struct Report {
+    pub outcomes: Vec<DirOutcome>,
+    pub committed: bool,
+}
+
+impl Report {
+    /// Bytes reclaimed (or that would be reclaimed in a dry run) across the
+    /// directories that were successfully processed.
+    pub fn reclaimed_bytes(&self) -> u64 {
+        self.outcomes
+            .iter()
+            .filter(|o| o.result.is_ok())
+            .map(|o| o.bytes)
+            .sum()
+    }
+
+    /// Number of directories successfully processed.
+    pub fn success_count(&self) -> usize {
+        self.outcomes.iter().filter(|o| o.result.is_ok()).count()
+    }
+
+    /// Print the closing `reclaimed X GiB across N directories` summary.
+    pub fn print_summary(&self) {
+        let verb = if self.committed { "reclaimed" } else { "would reclaim" };
+        println!(
+            "{} {:.2} GiB across {} directories",
+            verb,
+            self.reclaimed_bytes() as f64 / GIB,
+            self.success_count(),
+        );
+    }
+}
+
+/// Remove a matched entry. If the entry is itself a symlink, only the link is
+/// unlinked; the directory it points at is left untouched. Otherwise the tree
+/// is removed recursively.
+fn remove(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.file_type().is_symlink() {
+        remove_symlink(path)
+    } else {
+        fs::remove_dir_all(path)
+    }
+}
+
+#[cfg(unix)]
+fn remove_symlink(path: &Path) -> io::Result<()> {
+    fs::remove_file(path)
+}
+
+#[cfg(windows)]
+fn remove_symlink(path: &Path) -> io::Result<()> {
+    // A directory symlink must be removed with `remove_dir` on Windows, while
+    // a file symlink needs `remove_file`; try the directory form first.
+    fs::remove_dir(path).or_else(|_| fs::remove_file(path))
+}
+
+/// Move `path` into `backup_root`, preferring an atomic rename and falling
+/// back to a recursive copy-then-remove whenever the rename fails (as it does
+/// when the destination lives on another filesystem). `idx` keeps destination names unique across the batch. Returns
+/// the path the directory was moved to.
+fn trash_dir(path: &Path, backup_root: &Path, idx: usize) -> io::Result<PathBuf> {
+    fs::create_dir_all(backup_root)?;
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "entry".to_string());
+    let dest = backup_root.join(format!("{idx:04}-{name}"));
+
+    match fs::rename(path, &dest) {
+        Ok(()) => Ok(dest),
+        Err(_) => {
+            copy_dir(path, &dest)?;
+            fs::remove_dir_all(path)?;
+            Ok(dest)
+        }
+    }
+}
+
+/// Recursively copy a directory tree from `src` to `dst`.
+fn copy_dir(src: &Path, dst: &Path) -> io::Result<()> {
+    fs::create_dir_all(dst)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let target = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Sum the size of every regular file beneath `path`, without following
+/// symlinks out of the tree.
+pub fn dir_size(path: &Path) -> u64 {
+    // WalkDir always follows a symlinked root; a symlink itself holds no files.
+    if fs::symlink_metadata(path).map(|m| m.file_type().is_symlink()).unwrap_or(false) {
+        return 0;
+    }
+    WalkDir::new(path)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Size and process every directory in `dirs` according to `mode`, using up to
+/// `workers` threads. A progress line is printed as directories complete.
+pub fn run(dirs: Vec<PathBuf>, mode: Mode, workers: usize) -> Report {
+    let committed = mode.commits();
+    let total = dirs.len();
+    if total == 0 {
+        return Report { outcomes: Vec::new(), committed };
+    }
+
+    let next = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    let reclaimed = AtomicU64::new(0);
+    let outcomes: Mutex<Vec<DirOutcome>> = Mutex::new(Vec::with_capacity(total));
+    let workers = workers.clamp(1, total);
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let idx = next.fetch_add(1, Ordering::Relaxed);
+                if idx >= total {
+                    break;
+                }
+                let path = &dirs[idx];
+                let bytes = dir_size(path);
+                let (result, moved_to) = match &mode {
+                    Mode::DryRun => (Ok(()), None),
+                    Mode::Delete => (remove(path), None),
+                    Mode::Trash(root) => match trash_dir(path, root, idx) {
+                        Ok(dest) => (Ok(()), Some(dest)),
+                        Err(e) => (Err(e), None),
+                    },
+                };
+
+                let n = done.fetch_add(1, Ordering::Relaxed) + 1;
+                if result.is_ok() {
+                    reclaimed.fetch_add(bytes, Ordering::Relaxed);
+                }
+                let verb = match &mode {
+                    Mode::DryRun => "Scanning",
+                    Mode::Delete => "Deleting",
+                    Mode::Trash(_) => "Trashing",
+                };
+                print!(
+                    "\r[{}/{}] {} ({:.2} GiB)   ",
+                    n,
+                    total,
+                    verb,
+                    reclaimed.load(Ordering::Relaxed) as f64 / GIB,
+                );
+                let _ = io::stdout().flush();
+
+                outcomes.lock().unwrap().push(DirOutcome {
+                    path: path.clone(),
+                    bytes,
+                    result,
+                    moved_to,
+                });
+            });
+        }
+    });
+    println!();
+
+    Report {
+        outcomes: outcomes.into_inner().unwrap(),
+        committed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, File};
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("delete-artifacts-engine-{name}"));
+        let _ = fs::remove_dir_all(&dir);
+        create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_dir_size_sums_files() {
+        let dir = scratch("size");
+        let mut f = File::create(dir.join("a.txt")).unwrap();
+        f.write_all(&[0u8; 128]).unwrap();
+        create_dir_all(dir.join("nested")).unwrap();
+        let mut f = File::create(dir.join("nested/b.txt")).unwrap();
+        f.write_all(&[0u8; 64]).unwrap();
+
+        assert_eq!(dir_size(&dir), 192);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_dry_run_preserves_dirs() {
+        let root = scratch("dry");
+        let target = root.join("target");
+        create_dir_all(&target).unwrap();
+        File::create(target.join("f")).unwrap();
+
+        let report = run(vec![target.clone()], Mode::DryRun, 2);
+        assert!(target.exists());
+        assert_eq!(report.success_count(), 1);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_commit_removes_and_aggregates() {
+        let root = scratch("commit");
+        let a = root.join("a");
+        let b = root.join("b");
+        create_dir_all(&a).unwrap();
+        create_dir_all(&b).unwrap();
+        File::create(a.join("f")).unwrap();
+
+        let report = run(vec![a.clone(), b.clone()], Mode::Delete, 4);
+        assert!(!a.exists());
+        assert!(!b.exists());
+        assert_eq!(report.success_count(), 2);
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_trash_moves_instead_of_deleting() {
+        let root = scratch("trash");
+        let target = root.join("target");
+        create_dir_all(&target).unwrap();
+        File::create(target.join("artifact")).unwrap();
+        let backup = root.join("backup");
+
+        let report = run(vec![target.clone()], Mode::Trash(backup.clone()), 1);
+        assert!(!target.exists());
+        assert_eq!(report.success_count(), 1);
+        let moved = report.outcomes[0].moved_to.clone().unwrap();
+        assert!(moved.starts_with(&backup));
+        assert!(moved.join("artifact").exists());
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_target_is_only_unlinked() {
+        use std::os::unix::fs::symlink;
+
+        let root = scratch("symlink");
+        // A shared cache that lives outside the deletion target.
+        let shared = root.join("shared");
+        create_dir_all(&shared).unwrap();
+        File::create(shared.join("keep.txt")).unwrap();
+
+        // A "node_modules" that is really a symlink into the shared cache.
+        let link = root.join("node_modules");
+        symlink(&shared, &link).unwrap();
+
+        let report = run(vec![link.clone()], Mode::Delete, 1);
+        assert_eq!(report.success_count(), 1);
+        // The link is gone but the pointed-to contents survive.
+        assert!(!link.exists());
+        assert!(shared.join("keep.txt").exists());
+        fs::remove_dir_all(&root).ok();
+    }
+}
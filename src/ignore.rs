@@ -0,0 +1,264 @@
+//! A small `.gitignore` matcher used by `--respect-gitignore`.
+//!
+//! As the walk descends, [`IgnoreStack::enter`] is called for each directory;
+//! it loads that directory's `.gitignore` (if any) as a new layer and drops
+//! layers belonging to branches we've backtracked out of. [`IgnoreStack::is_ignored`]
+//! then answers whether a given path is ignored by the rules in scope, with
+//! deeper `.gitignore` files overriding shallower ones.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use globset::{Glob, GlobMatcher};
+
+/// A single `.gitignore` line compiled into a matcher.
+struct Rule {
+    matcher: GlobMatcher,
+    negated: bool,
+    dir_only: bool,
+}
+
+impl Rule {
+    /// Parse one `.gitignore` line, returning `None` for blanks, comments, and
+    /// patterns that fail to compile.
+    fn parse(line: &str) -> Option<Rule> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let mut pattern = line;
+        let negated = pattern.starts_with('!');
+        if negated {
+            pattern = &pattern[1..];
+        }
+
+        let dir_only = pattern.ends_with('/');
+        let pattern = pattern.trim_end_matches('/');
+
+        // A slash anywhere but the (already stripped) trailing position anchors
+        // the pattern to the `.gitignore`'s directory; otherwise it matches at
+        // any depth beneath it.
+        let anchored = pattern.contains('/');
+        let anchored_pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+        let glob = if anchored {
+            anchored_pattern.to_string()
+        } else {
+            format!("**/{pattern}")
+        };
+
+        let matcher = Glob::new(&glob).ok()?.compile_matcher();
+        Some(Rule {
+            matcher,
+            negated,
+            dir_only,
+        })
+    }
+
+    fn matches(&self, rel: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        self.matcher.is_match(rel)
+    }
+}
+
+/// Rules contributed by one directory's `.gitignore`.
+struct Layer {
+    depth: usize,
+    base: PathBuf,
+    rules: Vec<Rule>,
+}
+
+/// A stack of `.gitignore` layers tracking the current traversal position.
+#[derive(Default)]
+pub struct IgnoreStack {
+    layers: Vec<Layer>,
+}
+
+impl IgnoreStack {
+    pub fn new() -> IgnoreStack {
+        IgnoreStack::default()
+    }
+
+    /// Record entry into `dir` at the given walk depth: drop any layers from
+    /// branches we've left, then load this directory's `.gitignore`.
+    pub fn enter(&mut self, dir: &Path, depth: usize) {
+        self.layers.retain(|l| l.depth < depth);
+
+        let gitignore = dir.join(".gitignore");
+        if let Ok(contents) = fs::read_to_string(&gitignore) {
+            let rules: Vec<Rule> = contents.lines().filter_map(Rule::parse).collect();
+            if !rules.is_empty() {
+                self.layers.push(Layer {
+                    depth,
+                    base: dir.to_path_buf(),
+                    rules,
+                });
+            }
+        }
+    }
+
+    /// Whether `path` is ignored by the rules currently in scope. Deeper
+    /// layers override shallower ones, and within a layer the last matching
+    /// rule wins (honoring `!` negation).
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let mut ignored = false;
+        for layer in &self.layers {
+            let Ok(rel) = path.strip_prefix(&layer.base) else {
+                continue;
+            };
+            for rule in &layer.rules {
+                if rule.matches(rel, is_dir) {
+                    ignored = !rule.negated;
+                }
+            }
+        }
+        ignored
+    }
+}
+
+/// Whether `dir` contains any path git actually tracks, checked against the
+/// index with `git ls-files` scoped to `dir`. A directory can match an
+/// ignore pattern (and every rule in its `.gitignore`) while still holding a
+/// force-added (`git add -f`) file; `IgnoreStack` has no way to know that, so
+/// callers must consult this too before treating a pattern match as safe to
+/// delete. Returns `false` (not tracked) if `dir` isn't inside a git
+/// repository at all, so `--respect-gitignore` degrades to pattern-only
+/// matching outside of one, as before.
+pub fn has_tracked_entries(dir: &Path) -> bool {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["ls-files", "."])
+        .output();
+    matches!(output, Ok(o) if o.status.success() && !o.stdout.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("delete-artifacts-ignore-{name}"));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_unanchored_pattern_matches_at_any_depth() {
+        let root = scratch("unanchored");
+        fs::write(root.join(".gitignore"), "cache\n").unwrap();
+        fs::create_dir_all(root.join("a/b")).unwrap();
+
+        let mut stack = IgnoreStack::new();
+        stack.enter(&root, 0);
+
+        assert!(stack.is_ignored(&root.join("cache"), true));
+        assert!(stack.is_ignored(&root.join("a/b/cache"), true));
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_anchored_pattern_only_matches_at_gitignore_root() {
+        let root = scratch("anchored");
+        fs::write(root.join(".gitignore"), "/dist\n").unwrap();
+        fs::create_dir_all(root.join("sub")).unwrap();
+
+        let mut stack = IgnoreStack::new();
+        stack.enter(&root, 0);
+
+        assert!(stack.is_ignored(&root.join("dist"), true));
+        assert!(!stack.is_ignored(&root.join("sub/dist"), true));
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_dir_only_rule_does_not_match_files() {
+        let root = scratch("dir-only");
+        fs::write(root.join(".gitignore"), "build/\n").unwrap();
+
+        let mut stack = IgnoreStack::new();
+        stack.enter(&root, 0);
+
+        assert!(stack.is_ignored(&root.join("build"), true));
+        assert!(!stack.is_ignored(&root.join("build"), false));
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_negation_overrides_earlier_rule() {
+        let root = scratch("negation");
+        fs::write(root.join(".gitignore"), "*.log\n!important.log\n").unwrap();
+
+        let mut stack = IgnoreStack::new();
+        stack.enter(&root, 0);
+
+        assert!(stack.is_ignored(&root.join("debug.log"), false));
+        assert!(!stack.is_ignored(&root.join("important.log"), false));
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_enter_drops_layers_from_backtracked_branches() {
+        let root = scratch("backtrack");
+        let sub = root.join("sub");
+        fs::create_dir_all(&sub).unwrap();
+        fs::write(sub.join(".gitignore"), "only-in-sub\n").unwrap();
+
+        let mut stack = IgnoreStack::new();
+        stack.enter(&root, 0);
+        stack.enter(&sub, 1);
+        assert!(stack.is_ignored(&sub.join("only-in-sub"), true));
+
+        // Backtracking to a sibling at the same depth drops sub's layer.
+        let sibling = root.join("sibling");
+        fs::create_dir_all(&sibling).unwrap();
+        stack.enter(&sibling, 1);
+        assert!(!stack.is_ignored(&sibling.join("only-in-sub"), true));
+
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_has_tracked_entries_detects_force_added_file() {
+        let root = scratch("git-tracked");
+        assert!(Command::new("git")
+            .args(["init", "-q"])
+            .current_dir(&root)
+            .status()
+            .unwrap()
+            .success());
+
+        fs::write(root.join(".gitignore"), "node_modules/\n").unwrap();
+        let tracked_dir = root.join("node_modules");
+        fs::create_dir_all(&tracked_dir).unwrap();
+        fs::write(tracked_dir.join("keep.txt"), "keep").unwrap();
+        assert!(Command::new("git")
+            .args(["add", "-f", "node_modules/keep.txt"])
+            .current_dir(&root)
+            .status()
+            .unwrap()
+            .success());
+
+        assert!(has_tracked_entries(&tracked_dir));
+
+        let untracked_dir = root.join("target");
+        fs::create_dir_all(&untracked_dir).unwrap();
+        fs::write(untracked_dir.join("f"), "x").unwrap();
+        assert!(!has_tracked_entries(&untracked_dir));
+
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_has_tracked_entries_outside_a_repo_is_false() {
+        let root = scratch("no-git");
+        fs::write(root.join("f"), "x").unwrap();
+        assert!(!has_tracked_entries(&root));
+        fs::remove_dir_all(&root).ok();
+    }
+}
@@ -1,19 +1,32 @@
-use std::collections::HashSet;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
 use std::fs::{self, File};
-use std::io::{self, Write};
-use std::path::PathBuf;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
 use walkdir::WalkDir;
 
+mod engine;
+mod ignore;
+
+/// Default directory names matched when no config file or `--pattern` flag
+/// narrows the set down.
 static DIRS_TO_DELETE: [&str; 3] = ["node_modules", "vendor", "target"];
 
+/// Name of the optional config file loaded from `start_dir` or the user
+/// config directory.
+const CONFIG_FILE_NAME: &str = "delete-artifacts.toml";
+
 /// Simple program to delete specific build artifact directories
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
     /// Starting directory for search
-    #[clap(value_parser)]
-    start_dir: PathBuf,
+    #[clap(value_parser, required_unless_present = "restore")]
+    start_dir: Option<PathBuf>,
 
     /// Actually commit the deletion
     #[clap(short, long)]
@@ -22,44 +35,432 @@ struct Args {
     /// Don't create a log file with all the deleted directories.
     #[clap(short, long)]
     skip_log_file: bool,
+
+    /// Name or glob of directories to delete (repeatable). Extends any
+    /// patterns found in a `delete-artifacts.toml` config file.
+    #[clap(short, long)]
+    pattern: Vec<String>,
+
+    /// List every matched directory and ask for a single confirmation before
+    /// deleting any of them.
+    #[clap(long, conflicts_with = "ask_each")]
+    ask_once: bool,
+
+    /// Ask for confirmation before deleting each matched directory.
+    #[clap(long)]
+    ask_each: bool,
+
+    /// Allow sweeping from the filesystem root or the home directory. Without
+    /// this flag such a `start_dir` is refused.
+    #[clap(long)]
+    no_preserve_root: bool,
+
+    /// Only delete matched directories that are actually ignored by git,
+    /// according to the `.gitignore` files encountered along the way.
+    #[clap(long)]
+    respect_gitignore: bool,
+
+    /// Move matched directories into a timestamped backup directory instead of
+    /// deleting them, so they can be restored later with `--restore`.
+    #[clap(long)]
+    trash: bool,
+
+    /// Restore directories previously moved with `--trash`, reading the run log
+    /// written at that time.
+    #[clap(long, value_name = "LOGFILE")]
+    restore: Option<PathBuf>,
+
+    /// Format of the run log.
+    #[clap(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Write the run log to this path instead of `deleted_dirs_log.txt`.
+    #[clap(long, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+}
+
+/// Run-log output format.
+#[derive(ValueEnum, Clone, Debug)]
+enum LogFormat {
+    Text,
+    Json,
+}
+
+/// One per-directory record in a JSON run log.
+#[derive(Serialize, Deserialize)]
+struct LogEntry {
+    path: String,
+    bytes_reclaimed: u64,
+    timestamp: u64,
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    moved_to: Option<String>,
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// Aggregate figures for a JSON run log.
+#[derive(Serialize, Deserialize)]
+struct LogSummary {
+    reclaimed_bytes: u64,
+    directories: usize,
+    committed: bool,
+}
+
+/// Top-level JSON run log.
+#[derive(Serialize, Deserialize)]
+struct RunLog {
+    summary: LogSummary,
+    entries: Vec<LogEntry>,
+}
+
+/// Patterns loaded from a `delete-artifacts.toml` config file.
+#[derive(Deserialize, Debug, Default)]
+struct Config {
+    /// Directory names or globs to match, e.g. `["node_modules", "*.cache"]`.
+    #[serde(default)]
+    patterns: Vec<String>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
 
+    if let Some(logfile) = &args.restore {
+        return do_restore(logfile);
+    }
+
     do_delete(args)
 }
 
-fn do_delete(args: Args) -> Result<(), Box<dyn std::error::Error>> {
-    let dirs_to_delete: HashSet<&'static str> = HashSet::from(DIRS_TO_DELETE);
-    let mut deleted_dirs: Vec<PathBuf> = Vec::new();
+/// Move entries recorded in a `--trash` run log back to their original paths.
+/// Reads either log format, auto-detected from the file's contents.
+fn do_restore(logfile: &Path) -> Result<(), Box<dyn Error>> {
+    let contents = fs::read_to_string(logfile)?;
+    let restores = parse_restorable_entries(&contents);
+
+    if restores.is_empty() {
+        return Err(format!(
+            "no restorable entries found in {} (expected a --trash run log with moved_to \
+             destinations); plain-deletion or already-restored logs can't be restored",
+            logfile.display()
+        )
+        .into());
+    }
+
+    for (original, moved_to) in restores {
+        println!("Restoring {moved_to} -> {original}");
+        if let Some(parent) = Path::new(&original).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if let Err(e) = fs::rename(&moved_to, &original) {
+            eprintln!("Error restoring {original}: {e}");
+        }
+    }
+    Ok(())
+}
+
+/// Extract `(original_path, moved_to)` pairs from a run log, trying the JSON
+/// format first and falling back to the tab-separated text format.
+fn parse_restorable_entries(contents: &str) -> Vec<(String, String)> {
+    if let Ok(run) = serde_json::from_str::<RunLog>(contents) {
+        return run
+            .entries
+            .into_iter()
+            .filter(|e| e.success)
+            .filter_map(|e| e.moved_to.map(|moved_to| (e.path, moved_to)))
+            .collect();
+    }
+
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, '\t');
+            let original = parts.next().unwrap_or_default();
+            let moved_to = parts.next()?;
+            if original.is_empty() || moved_to.is_empty() {
+                return None;
+            }
+            Some((original.to_string(), moved_to.to_string()))
+        })
+        .collect()
+}
+
+/// Build the engine mode for a committing run, honoring `--trash`. The backup
+/// root is anchored under `start_dir` (which callers pass already
+/// canonicalized) so it stays next to the swept tree and `moved_to` in the
+/// run log is absolute, independent of the process's cwd at restore time.
+fn commit_mode(trash: bool, start_dir: &Path) -> Result<engine::Mode, Box<dyn Error>> {
+    if trash {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Ok(engine::Mode::Trash(
+            start_dir
+                .join("delete-artifacts-backup")
+                .join(secs.to_string()),
+        ))
+    } else {
+        Ok(engine::Mode::Delete)
+    }
+}
+
+/// Serialize the run log in the requested format and write it atomically.
+fn write_log(
+    report: &engine::Report,
+    format: &LogFormat,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let contents = match format {
+        LogFormat::Text => {
+            let mut out = String::new();
+            for outcome in &report.outcomes {
+                if outcome.result.is_ok() {
+                    match &outcome.moved_to {
+                        Some(dest) => {
+                            out.push_str(&format!("{}\t{}\n", outcome.path.display(), dest.display()))
+                        }
+                        None => out.push_str(&format!("{}\n", outcome.path.display())),
+                    }
+                }
+            }
+            out.into_bytes()
+        }
+        LogFormat::Json => {
+            let entries = report
+                .outcomes
+                .iter()
+                .map(|o| LogEntry {
+                    path: o.path.display().to_string(),
+                    bytes_reclaimed: if o.result.is_ok() { o.bytes } else { 0 },
+                    timestamp,
+                    success: o.result.is_ok(),
+                    error: o.result.as_ref().err().map(|e| e.to_string()),
+                    moved_to: o.moved_to.as_ref().map(|p| p.display().to_string()),
+                })
+                .collect();
+            let run = RunLog {
+                summary: LogSummary {
+                    reclaimed_bytes: report.reclaimed_bytes(),
+                    directories: report.success_count(),
+                    committed: report.committed,
+                },
+                entries,
+            };
+            serde_json::to_vec_pretty(&run)?
+        }
+    };
+
+    write_atomic(path, &contents)?;
+    Ok(())
+}
+
+/// Write `contents` to `path` atomically by staging a sibling temp file and
+/// renaming it into place, so a crash mid-write can't leave a partial log.
+fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let tmp = match path.file_name() {
+        Some(name) => {
+            let mut name = name.to_os_string();
+            name.push(".tmp");
+            path.with_file_name(name)
+        }
+        None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid log path")),
+    };
 
-    for entry in WalkDir::new(&args.start_dir)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_dir())
     {
-        if dirs_to_delete.contains(&entry.file_name().to_str().unwrap()) {
-            let dir_path = entry.path();
-
-            if args.commit {
-                println!("Deleting {}", dir_path.display());
-                if let Err(e) = fs::remove_dir_all(dir_path) {
-                    eprintln!("Error deleting directory: {}", e);
-                } else {
-                    deleted_dirs.push(dir_path.to_path_buf());
+        let mut file = File::create(&tmp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)
+}
+
+/// Load a config file from `start_dir` first, falling back to the user config
+/// directory. Missing files are not an error; an empty config is returned.
+fn load_config(start_dir: &Path) -> Result<Config, Box<dyn Error>> {
+    let local = start_dir.join(CONFIG_FILE_NAME);
+    let path = if local.is_file() {
+        Some(local)
+    } else {
+        dirs::config_dir()
+            .map(|d| d.join(CONFIG_FILE_NAME))
+            .filter(|p| p.is_file())
+    };
+
+    match path {
+        Some(p) => Ok(toml::from_str(&fs::read_to_string(p)?)?),
+        None => Ok(Config::default()),
+    }
+}
+
+/// Canonicalize `start_dir` and refuse to sweep from the filesystem root or
+/// the user's home directory unless the safeguard has been explicitly waived.
+/// Returns the canonical path to walk from, which also bounds the match logic
+/// to at or below `start_dir`.
+fn check_preserve_root(start_dir: &Path, no_preserve_root: bool) -> Result<PathBuf, Box<dyn Error>> {
+    let canonical = fs::canonicalize(start_dir)?;
+    if !no_preserve_root {
+        let is_root = canonical.parent().is_none();
+        let is_home = dirs::home_dir()
+            .and_then(|h| fs::canonicalize(h).ok())
+            .map(|h| h == canonical)
+            .unwrap_or(false);
+        if is_root || is_home {
+            return Err(format!(
+                "refusing to sweep {} (filesystem root or home directory); \
+                 pass --no-preserve-root to override",
+                canonical.display()
+            )
+            .into());
+        }
+    }
+    Ok(canonical)
+}
+
+/// Compile the resolved patterns into a single matcher.
+fn build_matcher(patterns: &[String]) -> Result<GlobSet, Box<dyn Error>> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern)?);
+    }
+    Ok(builder.build()?)
+}
+
+/// Flush stdout, read a line from stdin, and return true only when the user
+/// answers `y` (case-insensitive). Anything else is treated as "no".
+fn confirm(prompt: &str) -> io::Result<bool> {
+    confirm_with(prompt, &mut io::stdin().lock())
+}
+
+/// Same as `confirm`, but reads the answer from `reader` instead of stdin so
+/// the `--ask-once`/`--ask-each` prompting behavior can be tested without a
+/// real terminal.
+fn confirm_with(prompt: &str, reader: &mut impl BufRead) -> io::Result<bool> {
+    print!("{prompt} [y/N] ");
+    io::stdout().flush()?;
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    Ok(line.trim().eq_ignore_ascii_case("y"))
+}
+
+/// Resolve the effective pattern list: config patterns extended by any CLI
+/// `--pattern` flags, falling back to the built-in defaults when both are empty.
+fn resolve_patterns(args: &Args, config: Config) -> Vec<String> {
+    let mut patterns = config.patterns;
+    patterns.extend(args.pattern.iter().cloned());
+    if patterns.is_empty() {
+        patterns = DIRS_TO_DELETE.iter().map(|s| s.to_string()).collect();
+    }
+    patterns
+}
+
+fn do_delete(args: Args) -> Result<(), Box<dyn Error>> {
+    let start_dir_arg = args
+        .start_dir
+        .as_ref()
+        .ok_or("a start directory is required")?;
+    let start_dir = check_preserve_root(start_dir_arg, args.no_preserve_root)?;
+    let config = load_config(&start_dir)?;
+    let patterns = resolve_patterns(&args, config);
+    let matcher = build_matcher(&patterns)?;
+
+    // Collect every matching directory up front, then hand the batch to the
+    // deletion engine so removals (and sizing) can run in parallel.
+    let mut matched_dirs: Vec<PathBuf> = Vec::new();
+    let mut ignore_stack = ignore::IgnoreStack::new();
+    let mut walk = WalkDir::new(&start_dir).follow_links(false).into_iter();
+    while let Some(entry) = walk.next() {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(_) => continue,
+        };
+        // `follow_links(false)` makes WalkDir report a directory symlink as
+        // is_symlink()==true/is_dir()==false, so it has to be recognized
+        // explicitly here or it never reaches the matcher below.
+        let is_dir = entry.file_type().is_dir();
+        let is_dir_symlink = !is_dir
+            && entry.path_is_symlink()
+            && fs::metadata(entry.path()).map(|m| m.is_dir()).unwrap_or(false);
+        if !is_dir && !is_dir_symlink {
+            continue;
+        }
+
+        if args.respect_gitignore && is_dir {
+            ignore_stack.enter(entry.path(), entry.depth());
+        }
+
+        let name = entry.file_name().to_string_lossy();
+        let rel = entry
+            .path()
+            .strip_prefix(&start_dir)
+            .unwrap_or(entry.path());
+
+        if matcher.is_match(name.as_ref()) || matcher.is_match(rel) {
+            if args.respect_gitignore {
+                // Skip targets that don't match any .gitignore rule in scope.
+                if !ignore_stack.is_ignored(entry.path(), true) {
+                    continue;
                 }
-            } else {
-                println!("Would delete {}", dir_path.display());
+                // A directory can match every rule in its .gitignore and
+                // still hold a force-added file; the pattern stack alone
+                // can't see that, so consult the index too.
+                if ignore::has_tracked_entries(entry.path()) {
+                    continue;
+                }
+            }
+            matched_dirs.push(entry.path().to_path_buf());
+            // The whole tree is about to be removed; don't descend into it or
+            // a same-named nested artifact dir (e.g. node_modules/pkg/node_modules)
+            // would get queued a second time and race its own parent's removal.
+            // A directory symlink is never descended into, and WalkDir would
+            // apply the skip to its parent instead, dropping its siblings.
+            if is_dir {
+                walk.skip_current_dir();
             }
         }
     }
 
-    if args.commit && !args.skip_log_file {
-        let mut file = File::create("deleted_dirs_log.txt")?;
-        for dir in deleted_dirs {
-            writeln!(file, "{}", dir.display())?;
+    let workers = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let report = if args.ask_each {
+        let confirmed: Vec<PathBuf> = matched_dirs
+            .into_iter()
+            .filter(|d| confirm(&format!("Delete {}?", d.display())).unwrap_or(false))
+            .collect();
+        engine::run(confirmed, commit_mode(args.trash, &start_dir)?, workers)
+    } else if args.ask_once {
+        println!("The following directories matched:");
+        for dir in &matched_dirs {
+            println!("  {}", dir.display());
         }
+        if !confirm("Delete all of the above?").unwrap_or(false) {
+            println!("Aborted.");
+            return Ok(());
+        }
+        engine::run(matched_dirs, commit_mode(args.trash, &start_dir)?, workers)
+    } else if args.commit {
+        engine::run(matched_dirs, commit_mode(args.trash, &start_dir)?, workers)
+    } else {
+        engine::run(matched_dirs, engine::Mode::DryRun, workers)
+    };
+
+    for outcome in &report.outcomes {
+        if let Err(e) = &outcome.result {
+            eprintln!("Error deleting {}: {}", outcome.path.display(), e);
+        }
+    }
+    report.print_summary();
+
+    if report.committed && !args.skip_log_file {
+        let log_path = args
+            .log_file
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("deleted_dirs_log.txt"));
+        write_log(&report, &args.log_format, &log_path)?;
     }
 
     Ok(())
@@ -72,7 +473,277 @@ mod tests {
     use std::fs::create_dir_all;
 
     #[test]
-    fn test_ignore_delete() -> Result<(), Box<dyn std::error::Error>> {
+    fn test_check_preserve_root_refuses_root() {
+        let err = check_preserve_root(Path::new("/"), false).unwrap_err();
+        assert!(err.to_string().contains("refusing to sweep"));
+    }
+
+    #[test]
+    fn test_check_preserve_root_refuses_home() {
+        let home = dirs::home_dir().expect("test environment has a home directory");
+        let err = check_preserve_root(&home, false).unwrap_err();
+        assert!(err.to_string().contains("refusing to sweep"));
+    }
+
+    #[test]
+    fn test_check_preserve_root_override_allows_root_and_home() {
+        assert!(check_preserve_root(Path::new("/"), true).is_ok());
+        let home = dirs::home_dir().expect("test environment has a home directory");
+        assert!(check_preserve_root(&home, true).is_ok());
+    }
+
+    #[test]
+    fn test_confirm_with_accepts_y_case_insensitively() {
+        let mut input = io::Cursor::new(b"Y\n" as &[u8]);
+        assert!(confirm_with("ok?", &mut input).unwrap());
+
+        let mut input = io::Cursor::new(b"y\n" as &[u8]);
+        assert!(confirm_with("ok?", &mut input).unwrap());
+    }
+
+    #[test]
+    fn test_confirm_with_rejects_anything_else() {
+        for answer in ["n\n", "no\n", "yes\n", "\n", ""] {
+            let mut input = io::Cursor::new(answer.as_bytes());
+            assert!(!confirm_with("ok?", &mut input).unwrap(), "answer {answer:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn test_load_config_reads_toml_patterns() -> Result<(), Box<dyn Error>> {
+        let temp_path = PathBuf::from("./test_load_config");
+        let _ = fs::remove_dir_all(&temp_path);
+        create_dir_all(&temp_path)?;
+        fs::write(
+            temp_path.join(CONFIG_FILE_NAME),
+            "patterns = [\"*.cache\", \"**/dist\"]\n",
+        )?;
+
+        let config = load_config(&temp_path)?;
+        assert_eq!(config.patterns, vec!["*.cache", "**/dist"]);
+
+        fs::remove_dir_all(&temp_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_missing_file_is_empty_default() -> Result<(), Box<dyn Error>> {
+        let temp_path = PathBuf::from("./test_load_config_missing");
+        let _ = fs::remove_dir_all(&temp_path);
+        create_dir_all(&temp_path)?;
+
+        let config = load_config(&temp_path)?;
+        assert!(config.patterns.is_empty());
+
+        fs::remove_dir_all(&temp_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_patterns_cli_extends_config() {
+        let args = Args {
+            start_dir: Some(PathBuf::from(".")),
+            commit: false,
+            skip_log_file: true,
+            pattern: vec!["build".to_string()],
+            ask_once: false,
+            ask_each: false,
+            no_preserve_root: false,
+            respect_gitignore: false,
+            trash: false,
+            restore: None,
+            log_format: LogFormat::Text,
+            log_file: None,
+        };
+        let config = Config {
+            patterns: vec!["*.cache".to_string()],
+        };
+
+        assert_eq!(resolve_patterns(&args, config), vec!["*.cache", "build"]);
+    }
+
+    #[test]
+    fn test_resolve_patterns_falls_back_to_defaults_when_empty() {
+        let args = Args {
+            start_dir: Some(PathBuf::from(".")),
+            commit: false,
+            skip_log_file: true,
+            pattern: vec![],
+            ask_once: false,
+            ask_each: false,
+            no_preserve_root: false,
+            respect_gitignore: false,
+            trash: false,
+            restore: None,
+            log_format: LogFormat::Text,
+            log_file: None,
+        };
+
+        assert_eq!(
+            resolve_patterns(&args, Config::default()),
+            DIRS_TO_DELETE.to_vec()
+        );
+    }
+
+    #[test]
+    fn test_build_matcher_matches_glob_patterns() -> Result<(), Box<dyn Error>> {
+        let matcher = build_matcher(&["*.cache".to_string(), "**/dist".to_string()])?;
+
+        assert!(matcher.is_match("app.cache"));
+        assert!(matcher.is_match(Path::new("a/b/dist")));
+        assert!(!matcher.is_match("node_modules"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_file_glob_pattern_drives_do_delete() -> Result<(), Box<dyn Error>> {
+        let temp_path = PathBuf::from("./test_config_glob");
+        let _ = fs::remove_dir_all(&temp_path);
+        create_dir_all(&temp_path)?;
+        fs::write(
+            temp_path.join(CONFIG_FILE_NAME),
+            "patterns = [\"*.cache\"]\n",
+        )?;
+
+        // Matches the configured glob; not one of the built-in default names.
+        let cache_dir = temp_path.join("app.cache");
+        create_dir_all(&cache_dir)?;
+        File::create(cache_dir.join("f"))?;
+
+        // Would match a built-in default, but the config fully replaces those
+        // defaults rather than extending them.
+        let node_modules = temp_path.join("node_modules");
+        create_dir_all(&node_modules)?;
+        File::create(node_modules.join("f"))?;
+
+        let args = Args {
+            start_dir: Some(temp_path.to_path_buf()),
+            commit: true,
+            skip_log_file: true,
+            pattern: vec![],
+            ask_once: false,
+            ask_each: false,
+            no_preserve_root: false,
+            respect_gitignore: false,
+            trash: false,
+            restore: None,
+            log_format: LogFormat::Text,
+            log_file: None,
+        };
+        main_with_args(args)?;
+
+        assert!(!cache_dir.exists());
+        assert!(node_modules.exists());
+
+        fs::remove_dir_all(&temp_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_pattern_only_replaces_defaults() -> Result<(), Box<dyn Error>> {
+        let temp_path = PathBuf::from("./test_cli_pattern_only");
+        let _ = fs::remove_dir_all(&temp_path);
+        create_dir_all(&temp_path)?;
+
+        let cache_dir = temp_path.join("app.cache");
+        create_dir_all(&cache_dir)?;
+        File::create(cache_dir.join("f"))?;
+
+        let node_modules = temp_path.join("node_modules");
+        create_dir_all(&node_modules)?;
+        File::create(node_modules.join("f"))?;
+
+        let args = Args {
+            start_dir: Some(temp_path.to_path_buf()),
+            commit: true,
+            skip_log_file: true,
+            pattern: vec!["*.cache".to_string()],
+            ask_once: false,
+            ask_each: false,
+            no_preserve_root: false,
+            respect_gitignore: false,
+            trash: false,
+            restore: None,
+            log_format: LogFormat::Text,
+            log_file: None,
+        };
+        main_with_args(args)?;
+
+        assert!(!cache_dir.exists());
+        assert!(node_modules.exists());
+
+        fs::remove_dir_all(&temp_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_preserve_root_allows_ordinary_dir() -> Result<(), Box<dyn Error>> {
+        let temp_path = PathBuf::from("./test_preserve_root_ok");
+        let _ = fs::remove_dir_all(&temp_path);
+        create_dir_all(&temp_path)?;
+
+        assert!(check_preserve_root(&temp_path, false).is_ok());
+
+        fs::remove_dir_all(&temp_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_respect_gitignore_preserves_force_added_tracked_dir() -> Result<(), Box<dyn Error>> {
+        use std::process::Command;
+
+        let temp_path = PathBuf::from("./test_respect_gitignore_tracked");
+        let _ = fs::remove_dir_all(&temp_path);
+        create_dir_all(&temp_path)?;
+        assert!(Command::new("git")
+            .args(["init", "-q"])
+            .current_dir(&temp_path)
+            .status()?
+            .success());
+        fs::write(temp_path.join(".gitignore"), "node_modules/\ntarget/\n")?;
+
+        // Ignored-and-tracked: matches the ignore rule, but a file inside was
+        // force-added to the index, so the whole directory must be kept.
+        let tracked = temp_path.join("node_modules");
+        create_dir_all(&tracked)?;
+        File::create(tracked.join("keep.txt"))?;
+        assert!(Command::new("git")
+            .args(["add", "-f", "node_modules/keep.txt"])
+            .current_dir(&temp_path)
+            .status()?
+            .success());
+
+        // Ignored-and-untracked: should still be removed as before.
+        let untracked = temp_path.join("target");
+        create_dir_all(&untracked)?;
+        File::create(untracked.join("f"))?;
+
+        let args = Args {
+            start_dir: Some(temp_path.to_path_buf()),
+            commit: true,
+            skip_log_file: true,
+            pattern: vec!["node_modules".to_string(), "target".to_string()],
+            ask_once: false,
+            ask_each: false,
+            no_preserve_root: false,
+            respect_gitignore: true,
+            trash: false,
+            restore: None,
+            log_format: LogFormat::Text,
+            log_file: None,
+        };
+        main_with_args(args)?;
+
+        assert!(tracked.exists(), "force-added tracked directory must survive");
+        assert!(tracked.join("keep.txt").exists());
+        assert!(!untracked.exists(), "genuinely ignored directory should still be removed");
+
+        fs::remove_dir_all(&temp_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_ignore_delete() -> Result<(), Box<dyn Error>> {
         let temp_path = PathBuf::from("./test");
 
         // Create test directories
@@ -90,9 +761,18 @@ mod tests {
 
         // Run the program in dry run mode
         let args = Args {
-            start_dir: temp_path.to_path_buf(),
+            start_dir: Some(temp_path.to_path_buf()),
             commit: false,
             skip_log_file: true,
+            pattern: vec![],
+            ask_once: false,
+            ask_each: false,
+            no_preserve_root: false,
+            respect_gitignore: false,
+            trash: false,
+            restore: None,
+            log_format: LogFormat::Text,
+            log_file: None,
         };
         main_with_args(args)?;
 
@@ -106,7 +786,49 @@ mod tests {
     }
 
     #[test]
-    fn test_delete() -> Result<(), Box<dyn std::error::Error>> {
+    fn test_nested_artifact_dirs_are_not_double_processed() -> Result<(), Box<dyn Error>> {
+        let temp_path = PathBuf::from("./test_nested");
+        let _ = fs::remove_dir_all(&temp_path);
+
+        // A node_modules containing another package with its own nested
+        // node_modules, which must not be queued (and processed) separately
+        // from its already-doomed parent.
+        let nested = temp_path.join("node_modules/pkg/node_modules");
+        create_dir_all(&nested)?;
+        File::create(nested.join("test_file.txt"))?;
+
+        let log_path = temp_path.join("run-log.json");
+        let args = Args {
+            start_dir: Some(temp_path.to_path_buf()),
+            commit: true,
+            skip_log_file: false,
+            pattern: vec![],
+            ask_once: false,
+            ask_each: false,
+            no_preserve_root: false,
+            respect_gitignore: false,
+            trash: false,
+            restore: None,
+            log_format: LogFormat::Json,
+            log_file: Some(log_path.clone()),
+        };
+        main_with_args(args)?;
+
+        assert!(!temp_path.join("node_modules").exists());
+
+        let log: serde_json::Value = serde_json::from_str(&fs::read_to_string(&log_path)?)?;
+        // Exactly one directory was queued (the outer node_modules); the
+        // nested one must not appear as a separate, failed entry.
+        assert_eq!(log["entries"].as_array().unwrap().len(), 1);
+        assert_eq!(log["entries"][0]["success"], true);
+        assert_eq!(log["summary"]["directories"], 1);
+
+        fs::remove_dir_all(&temp_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete() -> Result<(), Box<dyn Error>> {
         let temp_path = PathBuf::from("./test");
 
         // Create test directories
@@ -124,9 +846,18 @@ mod tests {
 
         // Run the program in dry run mode
         let args = Args {
-            start_dir: temp_path.to_path_buf(),
+            start_dir: Some(temp_path.to_path_buf()),
             commit: true,
             skip_log_file: false,
+            pattern: vec![],
+            ask_once: false,
+            ask_each: false,
+            no_preserve_root: false,
+            respect_gitignore: false,
+            trash: false,
+            restore: None,
+            log_format: LogFormat::Text,
+            log_file: None,
         };
         main_with_args(args)?;
 
@@ -139,8 +870,174 @@ mod tests {
         Ok(())
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_artifact_is_only_unlinked_via_do_delete() -> Result<(), Box<dyn Error>> {
+        use std::os::unix::fs::symlink;
+
+        let temp_path = PathBuf::from("./test_symlink");
+        let _ = fs::remove_dir_all(&temp_path);
+
+        // A shared cache that the link points at; its own name matches no pattern.
+        let shared = temp_path.join("shared");
+        create_dir_all(&shared)?;
+        File::create(shared.join("keep.txt"))?;
+
+        // A "node_modules" that is really a symlink into the shared cache.
+        let link = temp_path.join("node_modules");
+        symlink(&shared, &link)?;
+
+        let args = Args {
+            start_dir: Some(temp_path.to_path_buf()),
+            commit: true,
+            skip_log_file: true,
+            pattern: vec![],
+            ask_once: false,
+            ask_each: false,
+            no_preserve_root: false,
+            respect_gitignore: false,
+            trash: false,
+            restore: None,
+            log_format: LogFormat::Text,
+            log_file: None,
+        };
+        main_with_args(args)?;
+
+        // The link itself is gone, but walking through do_delete's real scan
+        // must not have followed it into the shared cache and destroyed that.
+        assert!(!link.exists());
+        assert!(shared.join("keep.txt").exists());
+
+        fs::remove_dir_all(&temp_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_trash_backup_is_anchored_and_absolute() -> Result<(), Box<dyn Error>> {
+        let temp_path = PathBuf::from("./test_trash_anchor");
+        let _ = fs::remove_dir_all(&temp_path);
+        let target = temp_path.join("node_modules");
+        create_dir_all(&target)?;
+        File::create(target.join("f"))?;
+
+        let log_path = temp_path.join("run-log.json");
+        let args = Args {
+            start_dir: Some(temp_path.to_path_buf()),
+            commit: true,
+            skip_log_file: false,
+            pattern: vec![],
+            ask_once: false,
+            ask_each: false,
+            no_preserve_root: false,
+            respect_gitignore: false,
+            trash: true,
+            restore: None,
+            log_format: LogFormat::Json,
+            log_file: Some(log_path.clone()),
+        };
+        main_with_args(args)?;
+
+        let canonical_start = fs::canonicalize(&temp_path)?;
+        let log: serde_json::Value = serde_json::from_str(&fs::read_to_string(&log_path)?)?;
+        let moved_to = log["entries"][0]["moved_to"].as_str().unwrap();
+        let moved_to_path = PathBuf::from(moved_to);
+
+        // Previously this was a bare "delete-artifacts-backup/<secs>" joined
+        // against nothing, landing wherever the process's cwd happened to be
+        // rather than next to the swept tree, and restore from a different
+        // cwd than the invocation failed outright.
+        assert!(moved_to_path.is_absolute());
+        assert!(moved_to_path.starts_with(&canonical_start));
+
+        fs::remove_dir_all(&temp_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_from_text_log() -> Result<(), Box<dyn Error>> {
+        let temp_path = PathBuf::from("./test_restore_text");
+        let _ = fs::remove_dir_all(&temp_path);
+        let target = temp_path.join("node_modules");
+        create_dir_all(&target)?;
+        File::create(target.join("f"))?;
+
+        let log_path = temp_path.join("run-log.txt");
+        let args = Args {
+            start_dir: Some(temp_path.to_path_buf()),
+            commit: true,
+            skip_log_file: false,
+            pattern: vec![],
+            ask_once: false,
+            ask_each: false,
+            no_preserve_root: false,
+            respect_gitignore: false,
+            trash: true,
+            restore: None,
+            log_format: LogFormat::Text,
+            log_file: Some(log_path.clone()),
+        };
+        main_with_args(args)?;
+        assert!(!target.exists());
+
+        do_restore(&log_path)?;
+        assert!(target.join("f").exists());
+
+        fs::remove_dir_all(&temp_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_from_json_log() -> Result<(), Box<dyn Error>> {
+        let temp_path = PathBuf::from("./test_restore_json");
+        let _ = fs::remove_dir_all(&temp_path);
+        let target = temp_path.join("node_modules");
+        create_dir_all(&target)?;
+        File::create(target.join("f"))?;
+
+        let log_path = temp_path.join("run-log.json");
+        let args = Args {
+            start_dir: Some(temp_path.to_path_buf()),
+            commit: true,
+            skip_log_file: false,
+            pattern: vec![],
+            ask_once: false,
+            ask_each: false,
+            no_preserve_root: false,
+            respect_gitignore: false,
+            trash: true,
+            restore: None,
+            log_format: LogFormat::Json,
+            log_file: Some(log_path.clone()),
+        };
+        main_with_args(args)?;
+        assert!(!target.exists());
+
+        // Previously, a JSON log silently restored nothing: the text parser
+        // split on '\t', never matched, and every line was dropped.
+        do_restore(&log_path)?;
+        assert!(target.join("f").exists());
+
+        fs::remove_dir_all(&temp_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_rejects_log_with_no_restorable_entries() {
+        let temp_path = PathBuf::from("./test_restore_reject");
+        let _ = fs::remove_dir_all(&temp_path);
+        create_dir_all(&temp_path).unwrap();
+        let log_path = temp_path.join("plain-delete.txt");
+        // A log from a plain (non-trash) run: paths with no backup location.
+        fs::write(&log_path, "/some/deleted/dir\n").unwrap();
+
+        let result = do_restore(&log_path);
+        assert!(result.is_err());
+
+        fs::remove_dir_all(&temp_path).ok();
+    }
+
     // Helper function to run the program with given arguments
-    fn main_with_args(args: Args) -> Result<(), Box<dyn std::error::Error>> {
-        do_delete(args).map_err(|e| e)
+    fn main_with_args(args: Args) -> Result<(), Box<dyn Error>> {
+        do_delete(args)
     }
 }